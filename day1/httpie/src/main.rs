@@ -1,11 +1,23 @@
 use anyhow::{anyhow, Result};
 use colored::Colorize;
+use futures_util::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
 use mime::Mime;
+use once_cell::sync::Lazy;
 use reqwest::Url;
 use reqwest::{header, Client, Response};
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::time::Instant;
 use structopt::StructOpt;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+use tokio::io::AsyncWriteExt;
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+mod session;
 
 // 定义HTTPied的CLI的主入口，它包含若干个字命令
 // 下面 /// 的注释是文档，clap会将其作为CLI的帮助
@@ -13,16 +25,55 @@ use structopt::StructOpt;
 #[derive(StructOpt, Debug)]
 #[structopt(name = "httpie")]
 struct Opts {
+    /// 不对输出做语法高亮, 也可以通过环境变量 NO_COLOR 开启
+    #[structopt(long = "no-color")]
+    no_color: bool,
+    /// 给当前请求指定一个session，相同名字的header/body会被保存下来供下次复用
+    #[structopt(long)]
+    session: Option<String>,
+    /// HTTP Basic认证，形如 user:pass
+    #[structopt(long)]
+    auth: Option<String>,
+    /// 使用Bearer token认证
+    #[structopt(long)]
+    bearer: Option<String>,
+    /// 通过代理发送请求，例如 http://127.0.0.1:8080
+    #[structopt(long)]
+    proxy: Option<String>,
+    /// 是否校验TLS证书，调试自签名证书时可以用 --verify=no 关闭
+    #[structopt(long, default_value = "yes")]
+    verify: String,
     #[structopt(subcommand)]
     subcmd: Subcommand,
 }
 
-// 子命令分别对应不同的HTTP方法，目前只支持get/post
+// 子命令分别对应不同的HTTP方法
 #[derive(StructOpt, Debug)]
 enum Subcommand {
     Get(Get),
     Post(Post),
-    // 我们暂时不支持其它的http方法
+    Put(MethodWithBody),
+    Patch(MethodWithBody),
+    Delete(MethodWithBody),
+    Head(MethodWithBody),
+    Options(MethodWithBody),
+    Session(SessionCmd),
+}
+
+// session 子命令组：查看/删除本地保存的session
+#[derive(StructOpt, Debug)]
+enum SessionCmd {
+    /// 列出所有已保存的session
+    List,
+    /// 查看某个session保存的内容
+    Show(SessionName),
+    /// 删除某个已保存的session
+    Rm(SessionName),
+}
+
+#[derive(StructOpt, Debug)]
+struct SessionName {
+    name: String,
 }
 
 // get子命令
@@ -33,6 +84,15 @@ struct Get {
     /// HTTP请求的URL
     #[structopt(parse(try_from_str = parse_url))]
     url: String,
+    /// 自定义HTTP header, 形如 name:value, 可以重复传入
+    #[structopt(short = "H", long, parse(try_from_str = parse_header_pair))]
+    headers: Vec<HeaderPair>,
+    /// 将response body流式下载到文件，而不是打印到终端
+    #[structopt(short = "d", long)]
+    download: bool,
+    /// 配合--download指定保存的文件名，缺省时从URL或Content-Disposition自动推断
+    #[structopt(long)]
+    output: Option<String>,
 }
 
 fn parse_url(s: &str) -> Result<String> {
@@ -50,9 +110,21 @@ struct Post {
     /// HTTP  请求的URL
     #[structopt(parse(try_from_str = parse_url))]
     url: String,
-    /// HTTP 请求的body
-    #[structopt(parse(try_from_str = parse_kv_pair))]
-    body: Vec<KvPair>,
+    /// HTTP 请求的body，可以是key=value，也可以是field@path(上传本地文件)
+    #[structopt(parse(try_from_str = parse_body_field))]
+    body: Vec<BodyField>,
+    /// 自定义HTTP header, 形如 name:value, 可以重复传入
+    #[structopt(short = "H", long, parse(try_from_str = parse_header_pair))]
+    headers: Vec<HeaderPair>,
+    /// 以application/x-www-form-urlencoded发送body，而不是默认的JSON
+    #[structopt(short = "f", long)]
+    form: bool,
+    /// 将response body流式下载到文件，而不是打印到终端
+    #[structopt(short = "d", long)]
+    download: bool,
+    /// 配合--download指定保存的文件名，缺省时从URL或Content-Disposition自动推断
+    #[structopt(long)]
+    output: Option<String>,
 }
 
 /// 命令行中的key=value 可以通过parse_kv_pair 解析KvPair结构
@@ -83,38 +155,498 @@ fn parse_kv_pair(s: &str) -> Result<KvPair> {
     Ok(s.parse()?)
 }
 
+// body中的一项：要么是key=value(JSON/form字段)，要么是field@path(上传本地文件)
+#[derive(Debug, PartialEq)]
+enum BodyField {
+    Pair(KvPair),
+    File { field: String, path: String },
+}
+
+fn parse_body_field(s: &str) -> Result<BodyField> {
+    // 谁先出现就按谁处理：field@path里的path可能含=，key=value里的value也可能含@
+    // (例如 email=user@example.com 必须还是一个KvPair)
+    let at_pos = s.find('@');
+    let eq_pos = s.find('=');
+    let is_file = match (at_pos, eq_pos) {
+        (Some(at), Some(eq)) => at < eq,
+        (Some(_), None) => true,
+        _ => false,
+    };
+
+    if is_file {
+        let (field, path) = s.split_once('@').expect("at_pos implies '@' present");
+        if !field.is_empty() {
+            return Ok(BodyField::File {
+                field: field.to_string(),
+                path: path.to_string(),
+            });
+        }
+    }
+
+    Ok(BodyField::Pair(s.parse()?))
+}
+
+// 把body中的key=value和field@path分开，前者用于JSON/form body，后者用于multipart文件
+fn split_body_fields(fields: &[BodyField]) -> (Vec<KvPair>, Vec<(String, String)>) {
+    let mut pairs = vec![];
+    let mut files = vec![];
+    for field in fields {
+        match field {
+            BodyField::Pair(pair) => pairs.push(KvPair {
+                k: pair.k.clone(),
+                v: pair.v.clone(),
+            }),
+            BodyField::File { field, path } => files.push((field.clone(), path.clone())),
+        }
+    }
+    (pairs, files)
+}
+
+/// 命令行中的 -H name:value 可以通过parse_header_pair 解析HeaderPair结构
+#[derive(StructOpt, Debug, PartialEq)]
+struct HeaderPair {
+    k: String,
+    v: String,
+}
+
+impl FromStr for HeaderPair {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // 使用 : 进行split, 只split一次因为value里也可能包含冒号(如URL)
+        let mut split = s.splitn(2, ':');
+        let err = || anyhow!(format!("Failed to parse{}", s));
+        Ok(Self {
+            k: (split.next().ok_or_else(err)?).to_string(),
+            v: (split.next().ok_or_else(err)?).to_string(),
+        })
+    }
+}
+
+fn parse_header_pair(s: &str) -> Result<HeaderPair> {
+    Ok(s.parse()?)
+}
+
+// 将name:value形式的header map转换成reqwest可以直接使用的HeaderMap，并校验header name/value是否合法
+fn build_headers(pairs: &HashMap<String, String>) -> Result<header::HeaderMap> {
+    let mut headers = header::HeaderMap::new();
+    for (k, v) in pairs.iter() {
+        let name = header::HeaderName::from_str(k.trim())
+            .map_err(|_| anyhow!(format!("Invalid header name: {}", k)))?;
+        let value = header::HeaderValue::from_str(v.trim())
+            .map_err(|_| anyhow!(format!("Invalid header value: {}", v)))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+// put/patch/delete/head/options 子命令共用的参数结构：一个URL, 和若干可选的key=value
+// 用于提供JSON body。HEAD/OPTIONS 不会用到body，但为了复用同一个结构依然保留该字段
+
+/// feed put/patch/delete/head/options with an url and optional key=value pairs.
+/// for put/patch/delete we will send the data as JSON, and retrieve the response for you
+#[derive(StructOpt, Debug)]
+struct MethodWithBody {
+    /// HTTP请求的URL
+    #[structopt(parse(try_from_str = parse_url))]
+    url: String,
+    /// HTTP 请求的body，可以是key=value，也可以是field@path(上传本地文件)
+    #[structopt(parse(try_from_str = parse_body_field))]
+    body: Vec<BodyField>,
+    /// 自定义HTTP header, 形如 name:value, 可以重复传入
+    #[structopt(short = "H", long, parse(try_from_str = parse_header_pair))]
+    headers: Vec<HeaderPair>,
+    /// 以application/x-www-form-urlencoded发送body，而不是默认的JSON
+    #[structopt(short = "f", long)]
+    form: bool,
+    /// 将response body流式下载到文件，而不是打印到终端
+    #[structopt(short = "d", long)]
+    download: bool,
+    /// 配合--download指定保存的文件名，缺省时从URL或Content-Disposition自动推断
+    #[structopt(long)]
+    output: Option<String>,
+}
+
 // cargo run -- post httpbin.org/post a=1 b=2
 #[tokio::main]
 async fn main() -> Result<()> {
     let opts = Opts::from_args();
-    println!("Opts: {:?}", opts);
 
-    let client = Client::new();
+    // session 子命令只是管理本地文件，不需要发请求
+    if let Subcommand::Session(ref cmd) = opts.subcmd {
+        return handle_session_cmd(cmd);
+    }
+
+    let client = build_client(opts.proxy.as_deref(), &opts.verify)?;
+    // --no-color 或者环境变量 NO_COLOR 都可以关闭语法高亮
+    let no_color = opts.no_color || std::env::var("NO_COLOR").is_ok();
+    let auth = match &opts.auth {
+        // --auth 复用 header 的 "split on first :" 解析方式
+        Some(s) => {
+            let pair: HeaderPair = s
+                .parse()
+                .map_err(|_| anyhow!(format!("Invalid --auth value: {}", s)))?;
+            Some((pair.k, pair.v))
+        }
+        None => None,
+    };
+    let ctx = RequestContext {
+        no_color,
+        session_name: opts.session.as_deref(),
+        auth,
+        bearer: opts.bearer.as_deref(),
+    };
 
     let result = match opts.subcmd {
-        Subcommand::Get(ref args) => get(client, args).await?,
-        Subcommand::Post(ref args) => post(client, args).await?,
+        Subcommand::Get(ref args) => get(client, args, &ctx).await?,
+        Subcommand::Post(ref args) => post(client, args, &ctx).await?,
+        Subcommand::Put(ref args) => put(client, args, &ctx).await?,
+        Subcommand::Patch(ref args) => patch(client, args, &ctx).await?,
+        Subcommand::Delete(ref args) => delete(client, args, &ctx).await?,
+        Subcommand::Head(ref args) => head(client, args, &ctx).await?,
+        Subcommand::Options(ref args) => options(client, args, &ctx).await?,
+        Subcommand::Session(_) => unreachable!("handled above"),
     };
 
     Ok(result)
 }
 
-async fn get(client: Client, args: &Get) -> Result<()> {
-    let resp = client.get(&args.url).send().await?;
+// 根据--proxy/--verify构建client，没有指定时和之前一样使用默认配置
+fn build_client(proxy: Option<&str>, verify: &str) -> Result<Client> {
+    let mut builder = Client::builder();
+    if let Some(proxy_url) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+    }
+    if verify == "no" {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder.build()?)
+}
+
+// 由全局参数(--no-color/--session/--auth/--bearer)派生出来的、贯穿一次请求的上下文
+struct RequestContext<'a> {
+    no_color: bool,
+    session_name: Option<&'a str>,
+    auth: Option<(String, String)>,
+    bearer: Option<&'a str>,
+}
+
+// 把HTTP Basic/Bearer认证信息应用到request builder上
+fn apply_auth(builder: reqwest::RequestBuilder, ctx: &RequestContext) -> reqwest::RequestBuilder {
+    let builder = match &ctx.auth {
+        Some((user, pass)) => builder.basic_auth(user, Some(pass)),
+        None => builder,
+    };
+
+    match ctx.bearer {
+        Some(token) => builder.bearer_auth(token),
+        None => builder,
+    }
+}
+
+fn handle_session_cmd(cmd: &SessionCmd) -> Result<()> {
+    match cmd {
+        SessionCmd::List => {
+            for name in session::list()? {
+                println!("{}", name);
+            }
+        }
+        SessionCmd::Show(args) => match session::load(&args.name)? {
+            Some(s) => println!("{:#?}", s),
+            None => println!("session `{}` not found", args.name),
+        },
+        SessionCmd::Rm(args) => session::remove(&args.name)?,
+    }
+
+    Ok(())
+}
+
+// 把session里保存的headers/body和当前命令行传入的header_pairs/body_pairs合并，
+// 当前命令行传入的值优先(会覆盖session里的同名key)
+fn merge_session(
+    session_name: Option<&str>,
+    header_pairs: &[HeaderPair],
+    body_pairs: &[KvPair],
+) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
+    let mut headers_map = HashMap::new();
+    let mut body_map = HashMap::new();
+
+    if let Some(name) = session_name {
+        if let Some(session) = session::load(name)? {
+            headers_map = session.headers;
+            body_map = session.body;
+        }
+    }
+
+    for pair in header_pairs {
+        headers_map.insert(pair.k.clone(), pair.v.clone());
+    }
+    for pair in body_pairs {
+        body_map.insert(pair.k.clone(), pair.v.clone());
+    }
+
+    Ok((headers_map, body_map))
+}
+
+// 请求成功之后，把合并后的method/url/headers/body写回session文件
+fn persist_session(
+    session_name: Option<&str>,
+    method: &str,
+    url: &str,
+    headers_map: &HashMap<String, String>,
+    body_map: &HashMap<String, String>,
+) -> Result<()> {
+    if let Some(name) = session_name {
+        let session = session::Session {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: headers_map.clone(),
+            body: body_map.clone(),
+        };
+        session::save(name, &session)?;
+    }
+
+    Ok(())
+}
+
+async fn get(client: Client, args: &Get, ctx: &RequestContext<'_>) -> Result<()> {
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &[])?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(client.get(&args.url).headers(headers), ctx);
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "GET", &args.url, &headers_map, &body_map)?;
+    }
     // println!("{:?}", resp.text().await?);
     // Ok(())
-    Ok(print_resp(resp).await?)
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
 }
 
-async fn post(client: Client, args: &Post) -> Result<()> {
-    let mut body = HashMap::new();
-    for pair in args.body.iter() {
-        body.insert(&pair.k, &pair.v);
+async fn post(client: Client, args: &Post, ctx: &RequestContext<'_>) -> Result<()> {
+    let (kv_pairs, file_fields) = split_body_fields(&args.body);
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &kv_pairs)?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(client.post(&args.url).headers(headers), ctx);
+    let builder = apply_body(builder, &body_map, &file_fields, args.form).await?;
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "POST", &args.url, &headers_map, &body_map)?;
     }
-    let resp = client.post(&args.url).json(&body).send().await?;
     // println!("{:?}", resp.text().await?);
     // Ok(())
-    Ok(print_resp(resp).await?)
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
+}
+
+async fn put(client: Client, args: &MethodWithBody, ctx: &RequestContext<'_>) -> Result<()> {
+    let (kv_pairs, file_fields) = split_body_fields(&args.body);
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &kv_pairs)?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(client.put(&args.url).headers(headers), ctx);
+    let builder = apply_body(builder, &body_map, &file_fields, args.form).await?;
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "PUT", &args.url, &headers_map, &body_map)?;
+    }
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
+}
+
+async fn patch(client: Client, args: &MethodWithBody, ctx: &RequestContext<'_>) -> Result<()> {
+    let (kv_pairs, file_fields) = split_body_fields(&args.body);
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &kv_pairs)?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(client.patch(&args.url).headers(headers), ctx);
+    let builder = apply_body(builder, &body_map, &file_fields, args.form).await?;
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "PATCH", &args.url, &headers_map, &body_map)?;
+    }
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
+}
+
+async fn delete(client: Client, args: &MethodWithBody, ctx: &RequestContext<'_>) -> Result<()> {
+    let (kv_pairs, file_fields) = split_body_fields(&args.body);
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &kv_pairs)?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(client.delete(&args.url).headers(headers), ctx);
+    let builder = apply_body(builder, &body_map, &file_fields, args.form).await?;
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "DELETE", &args.url, &headers_map, &body_map)?;
+    }
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
+}
+
+// HEAD/OPTIONS 不携带JSON body
+async fn head(client: Client, args: &MethodWithBody, ctx: &RequestContext<'_>) -> Result<()> {
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &[])?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(client.head(&args.url).headers(headers), ctx);
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "HEAD", &args.url, &headers_map, &body_map)?;
+    }
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
+}
+
+async fn options(client: Client, args: &MethodWithBody, ctx: &RequestContext<'_>) -> Result<()> {
+    let (headers_map, body_map) = merge_session(ctx.session_name, &args.headers, &[])?;
+    let headers = build_headers(&headers_map)?;
+    let builder = apply_auth(
+        client.request(reqwest::Method::OPTIONS, &args.url).headers(headers),
+        ctx,
+    );
+    let resp = builder.send().await?;
+    if resp.status().is_success() {
+        persist_session(ctx.session_name, "OPTIONS", &args.url, &headers_map, &body_map)?;
+    }
+    if args.download {
+        return download(resp, args.output.as_deref()).await;
+    }
+    Ok(print_resp(resp, ctx.no_color).await?)
+}
+
+// 根据body里有没有field@path文件字段，决定用multipart/form/json中的哪种方式发送请求体
+async fn apply_body(
+    builder: reqwest::RequestBuilder,
+    body_map: &HashMap<String, String>,
+    file_fields: &[(String, String)],
+    use_form: bool,
+) -> Result<reqwest::RequestBuilder> {
+    if !file_fields.is_empty() {
+        let form = build_multipart_form(body_map, file_fields).await?;
+        return Ok(builder.multipart(form));
+    }
+
+    if use_form {
+        return Ok(builder.form(body_map));
+    }
+
+    Ok(builder.json(body_map))
+}
+
+// 把key=value字段和本地文件field@path字段拼成一个multipart form，文件以流的方式读取
+async fn build_multipart_form(
+    body_map: &HashMap<String, String>,
+    file_fields: &[(String, String)],
+) -> Result<reqwest::multipart::Form> {
+    let mut form = reqwest::multipart::Form::new();
+    for (k, v) in body_map.iter() {
+        form = form.text(k.clone(), v.clone());
+    }
+
+    for (field, path) in file_fields {
+        let file = tokio::fs::File::open(path).await?;
+        let filename = std::path::Path::new(path)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("file")
+            .to_string();
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        let stream = FramedRead::new(file, BytesCodec::new());
+        let body = reqwest::Body::wrap_stream(stream);
+        let part = reqwest::multipart::Part::stream(body)
+            .file_name(filename)
+            .mime_str(mime.as_ref())?;
+        form = form.part(field.clone(), part);
+    }
+
+    Ok(form)
+}
+
+// 将response body流式写入文件，并渲染下载进度条；下载模式不做彩色打印
+async fn download(resp: Response, output: Option<&str>) -> Result<()> {
+    let filename = derive_filename(&resp, output);
+    let total = resp.content_length();
+
+    let pb = match total {
+        Some(len) => {
+            let pb = ProgressBar::new(len);
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("{bar:40.cyan/blue} {bytes}/{total_bytes} ({eta})")
+                    .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            );
+            pb
+        }
+        // 服务器没有返回Content-Length时，用一个不确定进度的spinner代替进度条
+        None => {
+            let pb = ProgressBar::new_spinner();
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb
+        }
+    };
+
+    let start = Instant::now();
+    let mut file = tokio::fs::File::create(&filename).await?;
+    let mut written: u64 = 0;
+    let mut stream = resp.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+        pb.set_position(written);
+    }
+
+    pb.finish_and_clear();
+    let elapsed = start.elapsed();
+    println!(
+        "Saved {} bytes to {} in {:.2}s",
+        written,
+        filename,
+        elapsed.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+// 推断下载文件名：优先使用--output，其次Content-Disposition，最后退化到URL最后一段
+fn derive_filename(resp: &Response, output: Option<&str>) -> String {
+    if let Some(path) = output {
+        return path.to_string();
+    }
+
+    if let Some(name) = resp
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_disposition_filename)
+    {
+        return name;
+    }
+
+    resp.url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("index.html")
+        .to_string()
+}
+
+fn parse_content_disposition_filename(value: &str) -> Option<String> {
+    value
+        .split(';')
+        .map(|part| part.trim())
+        .find_map(|part| part.strip_prefix("filename="))
+        .map(|name| name.trim_matches('"').to_string())
 }
 
 // 打印服务器的版本号 + 状态码
@@ -132,34 +664,78 @@ fn print_headers(resp: &Response) {
     println!("\n");
 }
 
-fn print_body(m: Option<Mime>, body: &str) {
-    match m {
-        // 对于 "application/json" 我们pretty print
-        Some(v) if v == mime::APPLICATION_JSON => {
-            println!("{}", jsonxf::pretty_print(body).unwrap().cyan())
+fn print_body(m: Option<Mime>, body: &str, no_color: bool) {
+    // HEAD/OPTIONS 等请求通常没有response body，直接跳过
+    if body.is_empty() {
+        return;
+    }
+
+    // 对于 "application/json" 我们先pretty print，再走语法高亮
+    let pretty = match m {
+        Some(ref v) if v == &mime::APPLICATION_JSON => {
+            jsonxf::pretty_print(body).unwrap_or_else(|_| body.to_string())
         }
-        // 其它 mime type 直接输出
-        _ => println!("{}", body),
+        _ => body.to_string(),
+    };
+
+    if no_color {
+        println!("{}", pretty);
+        return;
     }
+
+    println!("{}", highlight(&pretty, m.as_ref()));
 }
 
-async fn print_resp(resp: Response) -> Result<()> {
+async fn print_resp(resp: Response, no_color: bool) -> Result<()> {
     print_status(&resp);
     print_headers(&resp);
 
     let mime = get_content_type(&resp);
 
     let body = resp.text().await?;
-    print_body(mime, &body);
+    print_body(mime, &body, no_color);
 
     Ok(())
 }
 
-// 将服务器返回的content-type 解析成Mime 类型
+// SyntaxSet/ThemeSet的加载要解析一堆内置的语法/主题定义，比较昂贵，整个进程只做一次
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+// 根据content-type挑选对应的语法token，逐行高亮后拼接成可以直接打印到终端的字符串
+fn highlight(body: &str, m: Option<&Mime>) -> String {
+    let ps = &*SYNTAX_SET;
+    let ts = &*THEME_SET;
+
+    let syntax = match m.map(|v| v.subtype()) {
+        Some(sub) if sub == mime::JSON => ps.find_syntax_by_extension("json"),
+        Some(sub) if sub == mime::HTML => ps.find_syntax_by_extension("html"),
+        Some(sub) if sub == mime::XML => ps.find_syntax_by_extension("xml"),
+        _ => None,
+    }
+    .unwrap_or_else(|| ps.find_syntax_plain_text());
+
+    let theme = &ts.themes["base16-ocean.dark"];
+    let mut h = HighlightLines::new(syntax, theme);
+
+    let mut output = String::new();
+    for line in LinesWithEndings::from(body) {
+        let ranges = h.highlight(line, ps);
+        output.push_str(&as_24_bit_terminal_escaped(&ranges, false));
+    }
+    // 重置终端颜色，避免污染后续输出
+    output.push_str("\x1b[0m");
+    output
+}
+
+// 将服务器返回的content-type 解析成Mime 类型；header不存在、非UTF-8或者无法解析都当作没有，走纯文本高亮
 fn get_content_type(resp: &Response) -> Option<Mime> {
     resp.headers()
-        .get(header::CONTENT_TYPE)
-        .map(|v| v.to_str().unwrap().parse().unwrap())
+        .get(header::CONTENT_TYPE)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
 }
 
 #[cfg(test)]
@@ -192,4 +768,52 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn parse_header_pair_works() {
+        assert!(parse_header_pair("a").is_err());
+        assert_eq!(
+            parse_header_pair("Authorization:Bearer abc").unwrap(),
+            HeaderPair {
+                k: "Authorization".into(),
+                v: "Bearer abc".into(),
+            }
+        );
+
+        assert_eq!(
+            parse_header_pair("Accept:").unwrap(),
+            HeaderPair {
+                k: "Accept".into(),
+                v: "".into(),
+            }
+        )
+    }
+
+    #[test]
+    fn parse_body_field_works() {
+        assert_eq!(
+            parse_body_field("a=1").unwrap(),
+            BodyField::Pair(KvPair {
+                k: "a".into(),
+                v: "1".into(),
+            })
+        );
+
+        assert_eq!(
+            parse_body_field("avatar@/tmp/avatar.png").unwrap(),
+            BodyField::File {
+                field: "avatar".into(),
+                path: "/tmp/avatar.png".into(),
+            }
+        );
+
+        // value里带@的key=value必须还是Pair，不能被误判成field@path文件
+        assert_eq!(
+            parse_body_field("email=a@b.com").unwrap(),
+            BodyField::Pair(KvPair {
+                k: "email".into(),
+                v: "a@b.com".into(),
+            })
+        )
+    }
 }
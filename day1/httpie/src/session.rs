@@ -0,0 +1,70 @@
+// session 模块：把一次请求的method/url/headers/body保存到本地文件，方便下次直接复用
+//
+// 默认存放在 ~/.config/httpie/sessions/<name>.json
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// 一个保存下来的请求快照
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: HashMap<String, String>,
+}
+
+fn sessions_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Cannot locate home directory"))?;
+    let dir = home.join(".config").join("httpie").join("sessions");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn session_path(name: &str) -> Result<PathBuf> {
+    Ok(sessions_dir()?.join(format!("{}.json", name)))
+}
+
+/// 读取一个已经保存的session，不存在时返回None
+pub fn load(name: &str) -> Result<Option<Session>> {
+    let path = session_path(name)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+/// 把session保存（覆盖）到本地文件
+pub fn save(name: &str, session: &Session) -> Result<()> {
+    let path = session_path(name)?;
+    fs::write(path, serde_json::to_string_pretty(session)?)?;
+    Ok(())
+}
+
+/// 列出所有已保存的session名字
+pub fn list() -> Result<Vec<String>> {
+    let dir = sessions_dir()?;
+    let mut names = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(name) = entry.path().file_stem().and_then(|s| s.to_str()) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// 删除一个已保存的session，不存在时视为成功
+pub fn remove(name: &str) -> Result<()> {
+    let path = session_path(name)?;
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}